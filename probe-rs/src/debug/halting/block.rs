@@ -0,0 +1,83 @@
+use super::{
+    super::{unit_info::UnitInfo, DebugError, DebugInfo},
+    instruction::Instruction,
+};
+use std::collections::BTreeSet;
+use std::ops::RangeInclusive;
+
+/// A contiguous run of instructions, belonging either entirely to a concrete function
+/// body or entirely to one inlined function body, and never straddling a branch or a
+/// branch target. [`Sequence::build_blocks`](super::sequence::Sequence::build_blocks)
+/// splits a sequence's instructions into blocks at inline-boundary changes and at the
+/// addresses in `block_start_addresses`, so that every block's last instruction is a
+/// real basic-block terminator, and stepping and breakpoint resolution can reason about
+/// "is this address inside an inlined call" one block at a time.
+#[derive(Debug, Clone)]
+pub(crate) struct Block {
+    pub(crate) instructions: Vec<Instruction>,
+    /// The address execution reaches by simply falling off the end of this block, if
+    /// known. Superseded for control-flow purposes by [`super::sequence::Sequence::successors`],
+    /// but still used by [`Debug`](std::fmt::Debug) output and as a fallback.
+    pub(crate) steps_to: Option<u64>,
+    pub(crate) is_inlined: bool,
+}
+
+impl Block {
+    /// Consume instructions from `block_instructions` belonging to a single block: a
+    /// contiguous run that either is, or is not, inside an inlined function body, and
+    /// that contains none of `block_start_addresses` except possibly as its own first
+    /// instruction. The block ends when the inline status changes, when the next
+    /// instruction's address is itself a required block start, or when the iterator is
+    /// exhausted.
+    pub(crate) fn new(
+        start_address: u64,
+        block_instructions: &mut std::iter::Peekable<std::slice::Iter<Instruction>>,
+        debug_info: &DebugInfo,
+        program_unit: &UnitInfo,
+        block_start_addresses: &BTreeSet<u64>,
+    ) -> Result<Self, DebugError> {
+        let is_inlined = |address: u64| {
+            program_unit
+                .get_function_dies(debug_info, address, true)
+                .map(|function_dies| function_dies.last().is_some_and(|die| die.is_inline()))
+                .unwrap_or(false)
+        };
+        let block_is_inlined = is_inlined(start_address);
+
+        let mut instructions = Vec::new();
+        while let Some(instruction) = block_instructions.peek() {
+            // The first instruction always belongs to this block (it is the one that
+            // started it); only check later instructions against `block_is_inlined` and
+            // `block_start_addresses`, so the first iteration doesn't redundantly
+            // re-derive what the caller already established by starting a block here.
+            if !instructions.is_empty()
+                && (is_inlined(instruction.address) != block_is_inlined
+                    || block_start_addresses.contains(&instruction.address))
+            {
+                break;
+            }
+            instructions.push((*block_instructions.next().unwrap()).clone());
+        }
+
+        let steps_to = block_instructions.peek().map(|instruction| instruction.address);
+
+        Ok(Block {
+            instructions,
+            steps_to,
+            is_inlined: block_is_inlined,
+        })
+    }
+
+    pub(crate) fn contains_address(&self, address: u64) -> bool {
+        self.included_addresses()
+            .is_some_and(|range| range.contains(&address))
+    }
+
+    /// The inclusive address range covered by this block's instructions, or `None` if
+    /// the block has no instructions (which should not normally happen).
+    pub(crate) fn included_addresses(&self) -> Option<RangeInclusive<u64>> {
+        let first = self.instructions.first()?.address;
+        let last = self.instructions.last()?.address;
+        Some(first..=last)
+    }
+}
@@ -0,0 +1,280 @@
+use super::sequence::{Terminator, TerminatorDecoder};
+
+/// [`TerminatorDecoder`] for the Armv6-M/Armv7-M Thumb and Thumb-2 instruction sets,
+/// covering the handful of terminating instructions we need to classify a block's end:
+/// `BX LR` and `POP {.., PC}` (return, conditional if `IT`-conditioned), 16-bit
+/// unconditional and conditional branches, `BL`/`BLX` (treated as a call that falls
+/// through), and `TBB`/`TBH` (jump-table dispatch).
+pub(crate) struct ThumbTerminatorDecoder;
+
+const BX_LR: u16 = 0x4770;
+
+fn read_u16(bytes: &[u8], offset: usize) -> Option<u16> {
+    let slice = bytes.get(offset..offset + 2)?;
+    Some(u16::from_le_bytes([slice[0], slice[1]]))
+}
+
+/// `POP {..., PC}` (16-bit, T1 encoding `1011110P rrrrrrrr`): the far more common
+/// Thumb-1 epilogue, next to `BX LR`. Bit 8 (`P`) says whether `PC` is in the register
+/// list; when it is, this instruction returns just like `BX LR` does.
+fn is_pop_pc(halfword: u16) -> bool {
+    halfword & 0xFE00 == 0xBC00 && halfword & 0x0100 != 0
+}
+
+/// `IT` (16-bit, T1 encoding `10111111 cond mask`), with `mask != 0000` (a `mask` of
+/// `0000` is reserved/hint-instruction space, not `IT`) and `cond != 1111` (reserved).
+/// Whatever instruction follows an `IT` is only executed if its own condition, derived
+/// from `cond` and position within the `IT` block, holds at runtime.
+fn is_it_instruction(halfword: u16) -> bool {
+    halfword & 0xFF00 == 0xBF00 && halfword & 0x000F != 0 && halfword & 0x00F0 != 0x00F0
+}
+
+/// Sign-extend a branch offset of `bits` width to `i32`.
+fn sign_extend(value: u32, bits: u32) -> i32 {
+    let shift = 32 - bits;
+    ((value << shift) as i32) >> shift
+}
+
+impl TerminatorDecoder for ThumbTerminatorDecoder {
+    fn classify(&self, block_start: u64, block_bytes: &[u8], fallthrough: u64) -> Terminator {
+        // Walk to the last halfword-aligned instruction in the block, since that is
+        // the one that actually terminates it; everything before it is assumed (by
+        // our caller) to already have been accounted for by earlier blocks.
+        let Some(last_halfword_offset) = block_bytes.len().checked_sub(2) else {
+            return Terminator::FallThrough(fallthrough);
+        };
+        let instruction_address = block_start + last_halfword_offset as u64;
+        let Some(halfword) = read_u16(block_bytes, last_halfword_offset) else {
+            return Terminator::FallThrough(fallthrough);
+        };
+
+        if halfword == BX_LR || is_pop_pc(halfword) {
+            // If the instruction right before it is an `IT`, this return only happens
+            // if that `IT`'s condition holds at runtime, so the block must not be
+            // treated as unconditionally leaving the sequence.
+            let conditioned_by_it = last_halfword_offset
+                .checked_sub(2)
+                .and_then(|offset| read_u16(block_bytes, offset))
+                .is_some_and(is_it_instruction);
+
+            return if conditioned_by_it {
+                Terminator::ConditionalReturn { fallthrough }
+            } else {
+                Terminator::Return
+            };
+        }
+
+        // 32-bit BL/BLX: `11110xxxxxxxxxxx 11x1xxxxxxxxxxxx`. A call, not a
+        // terminator in the control-flow sense: it always falls through to the next
+        // block once the callee returns.
+        if last_halfword_offset >= 2 {
+            if let Some(first_halfword) = read_u16(block_bytes, last_halfword_offset - 2) {
+                if first_halfword & 0xF800 == 0xF000 && halfword & 0xD000 == 0xD000 {
+                    return Terminator::FallThrough(fallthrough);
+                }
+
+                // TBB/TBH: `111010001101 Rn` `1111000000 H Rm` - jump table dispatch.
+                if first_halfword & 0xFFF0 == 0xE8D0 && halfword & 0xFFE0 == 0xF000 {
+                    return Terminator::IndirectBranch(Vec::new());
+                }
+            }
+        }
+
+        // 16-bit unconditional branch: `11100xxxxxxxxxxx`.
+        if halfword & 0xF800 == 0xE000 {
+            let offset = sign_extend((halfword & 0x07FF) as u32, 11) << 1;
+            let target = (instruction_address as i64 + 4 + offset as i64) as u64;
+            return Terminator::Branch(target);
+        }
+
+        // 16-bit conditional branch: `1101ccccxxxxxxxx`, excluding cond 1110/1111
+        // (undefined/SVC encoding space).
+        if halfword & 0xF000 == 0xD000 {
+            let condition = (halfword >> 8) & 0x0F;
+            if condition < 0x0E {
+                let offset = sign_extend((halfword & 0x00FF) as u32, 8) << 1;
+                let taken = (instruction_address as i64 + 4 + offset as i64) as u64;
+                return Terminator::ConditionalBranch {
+                    taken,
+                    not_taken: fallthrough,
+                };
+            }
+        }
+
+        Terminator::FallThrough(fallthrough)
+    }
+
+    fn resolve_jump_table(
+        &self,
+        block_bytes: &[u8],
+        block_start: u64,
+        table_bytes: &[u8],
+        max_targets: usize,
+    ) -> Vec<u64> {
+        // Re-locate the TBB/TBH at the end of the block the same way `classify` did.
+        let Some(last_halfword_offset) = block_bytes.len().checked_sub(4) else {
+            return Vec::new();
+        };
+        let (Some(first_halfword), Some(second_halfword)) = (
+            read_u16(block_bytes, last_halfword_offset),
+            read_u16(block_bytes, last_halfword_offset + 2),
+        ) else {
+            return Vec::new();
+        };
+        if !(first_halfword & 0xFFF0 == 0xE8D0 && second_halfword & 0xFFE0 == 0xF000) {
+            return Vec::new();
+        }
+
+        // `Rn`, the table's base register. We can only resolve the table when this is
+        // PC: the table then immediately follows the instruction (`table_bytes`). Any
+        // other base register would mean the table's address was loaded from a
+        // register set somewhere earlier, which a single block's bytes can't tell us.
+        if first_halfword & 0x000F != 0b1111 {
+            return Vec::new();
+        }
+
+        let is_halfword_table = second_halfword & 0x0010 != 0;
+        let index_register = second_halfword & 0x000F;
+
+        // Find the `CMP Rm, #imm` (16-bit T1 encoding `00101 Rn iiiiiiii`) that bounds
+        // the index register, scanning backwards from the TBB/TBH so the nearest match
+        // wins. Compilers emit this immediately before the branch to a default case
+        // when the index is out of range, so its immediate is the table's entry count
+        // minus one.
+        // If we can't find that bound check, we have no way to know how many entries
+        // the table actually has, so give up rather than guess it's full-length:
+        // reading `max_targets` entries would pull whatever code or data follows the
+        // table into the CFG as bogus successors.
+        let Some(entry_count) = (0..last_halfword_offset)
+            .step_by(2)
+            .rev()
+            .find_map(|offset| {
+                let halfword = read_u16(block_bytes, offset)?;
+                (halfword & 0xF800 == 0x2800 && (halfword >> 8) & 0x07 == index_register)
+                    .then(|| (halfword & 0x00FF) as usize + 1)
+            })
+        else {
+            return Vec::new();
+        };
+        let entry_count = entry_count.min(max_targets);
+
+        let entry_width = if is_halfword_table { 2 } else { 1 };
+        // The table is addressed relative to the TBB/TBH instruction's own `PC` value,
+        // which ARM defines as the address of that (4-byte) instruction plus 4 - i.e.
+        // exactly where `table_bytes` starts.
+        let table_pc = block_start + last_halfword_offset as u64 + 4;
+
+        (0..entry_count)
+            .filter_map(|index| {
+                let offset = index * entry_width;
+                let entry = if is_halfword_table {
+                    read_u16(table_bytes, offset)? as u64
+                } else {
+                    *table_bytes.get(offset)? as u64
+                };
+                Some(table_pc + entry * 2)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bx_lr_is_a_return() {
+        let decoder = ThumbTerminatorDecoder;
+        let bytes = 0x4770u16.to_le_bytes();
+        assert_eq!(decoder.classify(0x1000, &bytes, 0x1002), Terminator::Return);
+    }
+
+    #[test]
+    fn unconditional_branch_targets_the_encoded_offset() {
+        let decoder = ThumbTerminatorDecoder;
+        // 0xE002: B with 11-bit offset 0x002 -> 2 << 1 = 4; target = pc(0x1000+4) + 4 = 0x1008.
+        let bytes = 0xE002u16.to_le_bytes();
+        assert_eq!(
+            decoder.classify(0x1000, &bytes, 0x1002),
+            Terminator::Branch(0x1008)
+        );
+    }
+
+    #[test]
+    fn conditional_branch_keeps_both_targets() {
+        let decoder = ThumbTerminatorDecoder;
+        // 0xD001: BEQ with 8-bit offset 0x01 -> 1 << 1 = 2; taken = pc(0x1000+4) + 2 = 0x1006.
+        let bytes = 0xD001u16.to_le_bytes();
+        assert_eq!(
+            decoder.classify(0x1000, &bytes, 0x1002),
+            Terminator::ConditionalBranch {
+                taken: 0x1006,
+                not_taken: 0x1002,
+            }
+        );
+    }
+
+    #[test]
+    fn bl_falls_through_since_the_call_returns() {
+        let decoder = ThumbTerminatorDecoder;
+        // BL with a zero offset: first halfword 0xF000, second halfword 0xF800.
+        let bytes = [0x00, 0xF0, 0x00, 0xF8];
+        assert_eq!(
+            decoder.classify(0x1000, &bytes, 0x1004),
+            Terminator::FallThrough(0x1004)
+        );
+    }
+
+    #[test]
+    fn pop_with_pc_is_a_return() {
+        let decoder = ThumbTerminatorDecoder;
+        // 0xBD01: POP {r0, pc} - P bit (0x0100) set.
+        let bytes = 0xBD01u16.to_le_bytes();
+        assert_eq!(decoder.classify(0x1000, &bytes, 0x1002), Terminator::Return);
+    }
+
+    #[test]
+    fn pop_without_pc_is_not_a_return() {
+        let decoder = ThumbTerminatorDecoder;
+        // 0xBC01: POP {r0} - P bit clear, falls through like any other non-terminator.
+        let bytes = 0xBC01u16.to_le_bytes();
+        assert_eq!(
+            decoder.classify(0x1000, &bytes, 0x1002),
+            Terminator::FallThrough(0x1002)
+        );
+    }
+
+    #[test]
+    fn it_conditioned_return_is_conditional() {
+        let decoder = ThumbTerminatorDecoder;
+        // 0xBF08: IT EQ; 0x4770: BX LR.
+        let bytes = [0x08, 0xBF, 0x70, 0x47];
+        assert_eq!(
+            decoder.classify(0x1000, &bytes, 0x1004),
+            Terminator::ConditionalReturn { fallthrough: 0x1004 }
+        );
+    }
+
+    #[test]
+    fn resolve_jump_table_reads_byte_entries_off_pc() {
+        let decoder = ThumbTerminatorDecoder;
+        // 0xE8DF: TBB with Rn=PC (0xF). Second halfword 0xF001: H=0, Rm=1.
+        let block_bytes = [0xDF, 0xE8, 0x01, 0xF0];
+        // Table (byte entries, each doubled): targets at table_pc + 0*2 and table_pc + 3*2.
+        let table_bytes = [0x00, 0x03];
+        let targets = decoder.resolve_jump_table(&block_bytes, 0x1000, &table_bytes, 8);
+        // table_pc = block_start(0x1000) + last_halfword_offset(0) + 4 = 0x1004.
+        assert_eq!(targets, vec![0x1004, 0x100A]);
+    }
+
+    #[test]
+    fn resolve_jump_table_with_non_pc_base_is_unresolved() {
+        let decoder = ThumbTerminatorDecoder;
+        // Same TBB but with Rn=R2 instead of PC: we can't know where the table is.
+        let block_bytes = [0xD2, 0xE8, 0x01, 0xF0];
+        let table_bytes = [0x00, 0x03];
+        assert!(decoder
+            .resolve_jump_table(&block_bytes, 0x1000, &table_bytes, 8)
+            .is_empty());
+    }
+}
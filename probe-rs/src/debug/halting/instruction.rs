@@ -0,0 +1,91 @@
+use super::super::ColumnType;
+use std::num::NonZeroU64;
+
+/// What role a single instruction plays when looking for a place to halt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum InstructionRole {
+    /// Part of the function prologue: never a valid halt location.
+    Prologue,
+    /// A DWARF "recommended breakpoint location" (`is_stmt`), or the first instruction
+    /// of the epilogue (`epilogue_begin`): a valid place to halt.
+    Statement,
+    /// Any other instruction: not a recommended halt location, but still part of the
+    /// sequence (e.g. for disassembly).
+    Other,
+}
+
+impl InstructionRole {
+    pub(crate) fn is_halt_location(&self) -> bool {
+        matches!(self, InstructionRole::Statement)
+    }
+}
+
+/// A single instruction's source-location bookkeeping, derived from one row of a
+/// DWARF line-number program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Instruction {
+    pub(crate) address: u64,
+    pub(crate) file_index: u64,
+    pub(crate) line: Option<NonZeroU64>,
+    pub(crate) column: ColumnType,
+    pub(crate) role: InstructionRole,
+    /// The instruction's length in bytes. DWARF line-number programs do not record
+    /// this directly, so [`fill_byte_lengths`] fills it in as a second pass, from the
+    /// gap to the next instruction's address; the last instruction in a sequence
+    /// measures against the sequence's own end address instead, since there is no next
+    /// instruction to measure against.
+    pub(crate) byte_length: u64,
+}
+
+/// Smallest possible Thumb instruction width, used as the fallback
+/// [`Instruction::byte_length`] before [`fill_byte_lengths`] has run.
+const MIN_INSTRUCTION_BYTE_LENGTH: u64 = 2;
+
+impl Instruction {
+    pub(crate) fn from_line_row(prologue_completed: bool, row: &gimli::LineRow) -> Self {
+        let role = if !prologue_completed {
+            InstructionRole::Prologue
+        } else if row.is_stmt() || row.epilogue_begin() {
+            InstructionRole::Statement
+        } else {
+            InstructionRole::Other
+        };
+
+        Instruction {
+            address: row.address(),
+            file_index: row.file_index(),
+            line: row.line(),
+            column: ColumnType::from(match row.column() {
+                gimli::ColumnType::LeftEdge => 0,
+                gimli::ColumnType::Column(column) => column.get(),
+            }),
+            role,
+            byte_length: MIN_INSTRUCTION_BYTE_LENGTH,
+        }
+    }
+
+    pub(crate) fn byte_length(&self) -> u64 {
+        self.byte_length
+    }
+}
+
+/// Fill in each instruction's [`Instruction::byte_length`] from the gap to the next
+/// instruction's address, now that the whole sequence has been collected. The last
+/// instruction measures against `sequence_end` instead (the end of the
+/// `gimli::LineSequence` it belongs to), since there is no next instruction to measure
+/// against - leaving it at [`MIN_INSTRUCTION_BYTE_LENGTH`] would hand a truncated
+/// 2-byte slice to the terminator decoder for any sequence whose real last instruction
+/// is wider than that (e.g. a `B.W` tail call, a `BL`, or a `TBB`/`TBH`), causing it to
+/// be misclassified as a fall-through.
+pub(crate) fn fill_byte_lengths(instructions: &mut [Instruction], sequence_end: u64) {
+    for index in 0..instructions.len() {
+        let next_address = instructions
+            .get(index + 1)
+            .map(|instruction| instruction.address)
+            .unwrap_or(sequence_end);
+        let length = next_address.saturating_sub(instructions[index].address);
+        if length > 0 {
+            instructions[index].byte_length = length;
+        }
+    }
+}
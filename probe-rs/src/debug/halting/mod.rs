@@ -0,0 +1,19 @@
+pub(crate) mod block;
+pub(crate) mod decoder;
+pub(crate) mod instruction;
+pub(crate) mod sequence;
+
+use super::SourceLocation;
+use sequence::InlineFrame;
+
+/// A resolved, "live" breakpoint location: a recommended halt address together with its
+/// source location and, if it sits inside one or more inlined function bodies, the
+/// synthetic inline call chain a debugger UI should show for it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct VerifiedBreakpoint {
+    pub(crate) address: u64,
+    pub(crate) source_location: SourceLocation,
+    /// The synthetic inline call stack at this location, innermost first. Empty if
+    /// this location is not inside any inlined function body. See [`InlineFrame`].
+    pub(crate) inline_frames: Vec<InlineFrame>,
+}
@@ -1,41 +1,428 @@
 use super::{
     super::{unit_info::UnitInfo, DebugError, DebugInfo},
     block::Block,
-    instruction::Instruction,
+    instruction::{self, Instruction},
     VerifiedBreakpoint,
 };
 use crate::debug::{ColumnType, SourceLocation};
 use gimli::LineSequence;
 use std::{
     self,
+    collections::{BTreeMap, BTreeSet, VecDeque},
     fmt::{Debug, Formatter},
     num::NonZeroU64,
     ops::Range,
+    rc::Rc,
 };
 use typed_path::TypedPathBuf;
 
-/// Keep track of all the instruction locations required to satisfy the operations of [`SteppingMode`].
-/// This is a list of target instructions, belonging to a [`gimli::LineSequence`],
-/// and filters it to only user code instructions (no prologue code, and no non-statement instructions),
-/// so that we are left only with what DWARF terms as "recommended breakpoint location".
-pub(crate) struct Sequence<'debug_info> {
+/// One level of the synthetic call stack at a halt location that lies inside one or
+/// more inlined function bodies: the innermost frame is the inlined routine the halt
+/// address is actually in, and each frame after it is named by walking outward through
+/// the `DW_TAG_inlined_subroutine` DIEs whose ranges contain the address. There is no
+/// physical stack frame backing any of this; it exists purely so a debugger UI can show
+/// the synthetic call stack and offer "step into the inlined frame".
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct InlineFrame {
+    /// The name of the function this frame represents: the inlined routine, for every
+    /// frame but the outermost, which names the concrete function it was inlined into.
+    pub(crate) function_name: Option<String>,
+    /// Where execution "is" in this frame. For the innermost frame this is the actual
+    /// halt location; for every frame further out, it is the call site of the frame one
+    /// level in, read from that frame's `DW_AT_call_file`/`DW_AT_call_line`/
+    /// `DW_AT_call_column`.
+    pub(crate) location: SourceLocation,
+}
+
+/// An upper bound on the number of jump-table entries we will enumerate for a single
+/// indirect branch, so that a corrupted or misidentified table can't send the abstract
+/// interpreter in [`classify_terminator`] scanning off into the weeds forever.
+const MAX_JUMP_TABLE_ENTRIES: usize = 512;
+
+/// How a block's terminating instruction transfers control, so that [`Sequence`] can
+/// build a real intra-sequence control-flow graph instead of assuming every block has a
+/// single linear successor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Terminator {
+    /// No branch at all: execution simply runs into the following block.
+    FallThrough(u64),
+    /// An unconditional branch, including tail calls, to a single target.
+    Branch(u64),
+    /// A conditional branch: one target if taken, one if not.
+    ConditionalBranch { taken: u64, not_taken: u64 },
+    /// An indirect branch, e.g. a jump table dispatch. The targets are the unique table
+    /// entries that [`classify_terminator`]'s abstract interpreter could resolve.
+    IndirectBranch(Vec<u64>),
+    /// A `return`. Control leaves the sequence; there is no mandatory successor.
+    Return,
+    /// A call-like branch that *may* return into the next block, depending on a
+    /// runtime condition (e.g. an `IT`-conditioned epilogue). `fallthrough` is a
+    /// *possible*, not mandatory, successor: a block ending this way must not be
+    /// assumed to always reach it, but a halt location reachable only through it (e.g.
+    /// after a loop whose every exit is one of these) still has to be discoverable.
+    ConditionalReturn { fallthrough: u64 },
+}
+
+/// The addresses control may transfer to immediately after a block ending in
+/// `terminator`. Shared between [`SequenceData::build_control_flow_graph`], which
+/// records these as the block's graph edges, and [`branch_target_addresses`], which
+/// uses the same set to decide where a new block has to start.
+fn terminator_successors(terminator: &Terminator) -> Vec<u64> {
+    match terminator {
+        Terminator::FallThrough(target) | Terminator::Branch(target) => vec![*target],
+        Terminator::ConditionalBranch { taken, not_taken } => vec![*taken, *not_taken],
+        Terminator::IndirectBranch(targets) => targets.clone(),
+        Terminator::Return => Vec::new(),
+        Terminator::ConditionalReturn { fallthrough } => vec![*fallthrough],
+    }
+}
+
+/// Architecture-specific decoding of a block's terminating instruction. Implementations
+/// live alongside the relevant target support, since DWARF line information alone
+/// cannot tell us whether a block ends in a branch, a conditional branch, or a jump
+/// table dispatch.
+pub(crate) trait TerminatorDecoder {
+    /// Classify the instruction(s) at the end of a block, given its raw bytes and the
+    /// address that would be reached by simply falling off the end of the block.
+    fn classify(&self, block_start: u64, block_bytes: &[u8], fallthrough: u64) -> Terminator;
+
+    /// Run a tiny per-block abstract interpreter over `block_bytes`, tracking which GPR
+    /// holds a jump table base and the bound on its index, then return the unique
+    /// targets found in the table, capped at `max_targets`. `table_bytes` is the raw
+    /// program data immediately following the block, in case the table itself is
+    /// PC-relative and so lives right after the dispatching instruction.
+    fn resolve_jump_table(
+        &self,
+        block_bytes: &[u8],
+        block_start: u64,
+        table_bytes: &[u8],
+        max_targets: usize,
+    ) -> Vec<u64>;
+}
+
+/// The `gimli` reader type used throughout the `.debug_line` machinery in this module.
+type LineReader = gimli::EndianReader<gimli::LittleEndian, std::rc::Rc<[u8]>>;
+
+/// Parsed line-number program data for one compilation unit, kept around so that
+/// [`Sequence::from_address_cached`] does not need to re-run
+/// `debug_line_section.program(..)` and re-derive the unit's line sequences on every
+/// breakpoint/step resolution.
+struct CachedLineProgram {
+    complete_line_program: gimli::CompleteLineProgram<LineReader, usize>,
+    /// The unit's line sequences, sorted by `start`, so a lookup for a given address is
+    /// a binary search rather than the linear scan `Sequence::from_address` does.
+    line_sequences: Vec<LineSequence<LineReader>>,
+}
+
+/// A lazily populated cache of parsed [`CachedLineProgram`]s, keyed by compilation
+/// unit, and of the fully built [`SequenceData`] for each sequence we have already
+/// resolved, keyed by the sequence's starting address. This matters for stepping
+/// through a tight loop: without the first map, every single step would re-decode the
+/// whole line-number program for the loop's unit; without the second, it would also
+/// re-run `build_blocks`/`build_control_flow_graph` over the same handful of
+/// instructions on every step, which is the more expensive of the two once a sequence
+/// has any nontrivial control flow.
+///
+/// [`DebugInfo`] owns one of these, and must call [`SequenceCache::clear`] whenever it
+/// reloads its underlying ELF/DWARF data, since a stale cache would otherwise keep
+/// referring to line programs and blocks from the previous load.
+#[derive(Default)]
+pub(crate) struct SequenceCache {
+    by_offset: std::cell::RefCell<
+        std::collections::HashMap<gimli::DebugLineOffset<usize>, Rc<CachedLineProgram>>,
+    >,
+    /// Built [`SequenceData`], keyed by the owning unit's line-program offset together
+    /// with the sequence's starting address (a single unit has many sequences, so the
+    /// offset alone is not a unique key here).
+    by_sequence_start: std::cell::RefCell<
+        std::collections::HashMap<(gimli::DebugLineOffset<usize>, u64), Rc<SequenceData>>,
+    >,
+}
+
+impl SequenceCache {
+    /// Drop all cached line-program and sequence data. Call this whenever the owning
+    /// [`DebugInfo`] reloads, since every cache entry is only valid for the ELF/DWARF
+    /// image it was decoded from.
+    pub(crate) fn clear(&self) {
+        self.by_offset.borrow_mut().clear();
+        self.by_sequence_start.borrow_mut().clear();
+    }
+
+    /// Find the cached line-program data for the compilation unit at `offset`,
+    /// decoding and caching it first if this is the first lookup for that unit.
+    fn get_or_decode(
+        &self,
+        debug_info: &DebugInfo,
+        offset: gimli::DebugLineOffset<usize>,
+        address_size: u8,
+    ) -> Result<Rc<CachedLineProgram>, DebugError> {
+        if let Some(cached) = self.by_offset.borrow().get(&offset) {
+            return Ok(cached.clone());
+        }
+
+        let incomplete_line_program = debug_info
+            .debug_line_section
+            .program(offset, address_size, None, None)?;
+        let (complete_line_program, mut line_sequences) = incomplete_line_program.sequences()?;
+        line_sequences.sort_by_key(|line_sequence| line_sequence.start);
+
+        let cached = Rc::new(CachedLineProgram {
+            complete_line_program,
+            line_sequences,
+        });
+        self.by_offset.borrow_mut().insert(offset, cached.clone());
+        Ok(cached)
+    }
+
+    /// The already-built [`SequenceData`] for the sequence starting at
+    /// `sequence_start` in the unit whose line program is at `offset`, if we have
+    /// resolved it before.
+    fn sequence_data(
+        &self,
+        offset: gimli::DebugLineOffset<usize>,
+        sequence_start: u64,
+    ) -> Option<Rc<SequenceData>> {
+        self.by_sequence_start
+            .borrow()
+            .get(&(offset, sequence_start))
+            .cloned()
+    }
+
+    /// Record `data` as the built [`SequenceData`] for the sequence starting at
+    /// `sequence_start` in the unit whose line program is at `offset`.
+    fn cache_sequence_data(
+        &self,
+        offset: gimli::DebugLineOffset<usize>,
+        sequence_start: u64,
+        data: Rc<SequenceData>,
+    ) {
+        self.by_sequence_start
+            .borrow_mut()
+            .insert((offset, sequence_start), data);
+    }
+}
+
+/// The address- and instruction-derived data for a [`Sequence`]: everything that does
+/// not borrow from the [`DebugInfo`]/[`UnitInfo`] the sequence was built from. Split
+/// out from [`Sequence`] itself so that [`SequenceCache`] can cache the (expensive to
+/// build) blocks and control-flow graph behind an `Rc`, independently of the
+/// short-lived borrows a particular [`Sequence`] holds.
+struct SequenceData {
     /// The `address_range.start` is the starting address of the program counter for which this sequence is valid,
     /// and allows us to identify target instruction locations where the program counter lies inside the prologue.
     /// The `address_range.end` is the first address that is not covered by this sequence within the line number program,
     /// and allows us to identify when stepping over a instruction location would result in leaving a sequence.
     /// - This is typically the instruction address of the first instruction in the next sequence,
     ///   which may also be the first instruction in a new function.
-    pub(crate) address_range: Range<u64>,
+    address_range: Range<u64>,
     /// Identify the last valid halt location in the sequence. This is not the same as the
     /// start of epilogue, which may occur more than once in a sequence.
-    pub(crate) last_halt_instruction: Option<u64>,
+    last_halt_instruction: Option<u64>,
     /// See [`Block`].
     /// Note: The process of recursing the line sequence to create blocks,
     /// is likely to create blocks that our out of sequence, so we sort them to
     /// comply with the DWARF specification, 6.2.5 to ensure the addresses in
     /// the sequence are monotonically increasing. This does not affect the stepping,
     /// because we do not (and should not) rely on the order of the blocks to step through the sequence.
-    pub(crate) blocks: Vec<Block>,
+    blocks: Vec<Block>,
+    /// The intra-sequence control-flow graph: every block's starting address, mapped to
+    /// the addresses that may be reached immediately after control leaves it. Built by
+    /// [`SequenceData::build_control_flow_graph`] once all blocks are known. A block
+    /// can have zero successors (it ends in a `return`), one (fall-through or an
+    /// unconditional branch), or more (a conditional branch or a jump table dispatch).
+    successors: BTreeMap<u64, Vec<u64>>,
+    /// Lazily built reverse mapping from an instruction's address to its position in
+    /// [`Sequence::disassembly_listing`], so that repeated calls to
+    /// [`Sequence::disassembly_index_for_address`] - e.g. a UI highlighting the current
+    /// instruction on every step - are an O(log n) lookup instead of rebuilding and
+    /// linearly scanning the whole listing each time. Lives here rather than on
+    /// [`Sequence`] so that it is actually built once and reused across every
+    /// [`Sequence`] wrapper that shares this `Rc<SequenceData>` via [`SequenceCache`],
+    /// instead of being rebuilt on every call to [`Sequence::from_address_cached`].
+    disassembly_index: std::cell::RefCell<Option<BTreeMap<u64, usize>>>,
+}
+
+impl SequenceData {
+    /// Find the position of `address` among this sequence's instructions in address
+    /// order, building [`SequenceData::disassembly_index`] on first use and reusing it
+    /// afterwards. Only needs `address`, so it doesn't need to re-derive source
+    /// locations the way [`Sequence::disassembly_listing`] does.
+    fn disassembly_index_for_address(&self, address: u64) -> Option<usize> {
+        if self.disassembly_index.borrow().is_none() {
+            let mut instructions: Vec<&Instruction> =
+                self.blocks.iter().flat_map(|block| block.instructions.iter()).collect();
+            instructions.sort_by_key(|instruction| instruction.address);
+            let index = instructions
+                .iter()
+                .enumerate()
+                .map(|(index, instruction)| (instruction.address, index))
+                .collect();
+            *self.disassembly_index.borrow_mut() = Some(index);
+        }
+
+        self.disassembly_index
+            .borrow()
+            .as_ref()
+            .and_then(|index| index.get(&address))
+            .copied()
+    }
+
+    /// Process instructions into blocks, based on their definition, position in the
+    /// sequence, and other debug information. A block ends whenever the next
+    /// instruction crosses an inline-boundary change, or whenever it is itself a
+    /// terminator or a target one can branch to - see [`branch_target_addresses`] -
+    /// so that every block's last instruction is a real basic-block terminator that
+    /// [`SequenceData::build_control_flow_graph`] can classify on its own, rather than
+    /// only the final instruction of a much larger run.
+    fn build_blocks(
+        &mut self,
+        debug_info: &DebugInfo,
+        program_unit: &UnitInfo,
+        block_instructions: &mut std::iter::Peekable<std::slice::Iter<Instruction>>,
+    ) -> Result<(), DebugError> {
+        let remaining: Vec<Instruction> = block_instructions.clone().cloned().collect();
+        let block_start_addresses =
+            branch_target_addresses(debug_info, &remaining, self.address_range.end);
+
+        while let Some(instruction) = block_instructions.peek() {
+            let current_block = Block::new(
+                instruction.address,
+                block_instructions,
+                debug_info,
+                program_unit,
+                &block_start_addresses,
+            )?;
+            self.blocks.push(current_block);
+        }
+        Ok(())
+    }
+
+    /// Build the intra-sequence control-flow graph, by classifying the terminating
+    /// instruction of every block and recording its successor addresses in
+    /// [`SequenceData::successors`]. Must run after [`SequenceData::build_blocks`],
+    /// since it needs to know where every block starts in order to determine each
+    /// block's fall-through address.
+    fn build_control_flow_graph(&mut self, debug_info: &DebugInfo) {
+        for block in &self.blocks {
+            let Some(included_addresses) = block.included_addresses() else {
+                continue;
+            };
+            let block_start = *included_addresses.start();
+            // `included_addresses().end()` is the *start* address of the block's last
+            // instruction, not the address one past its last byte, so the block's last
+            // instruction's own `byte_length` has to be added to reach the true end of
+            // the block's bytes.
+            let block_end = *included_addresses.end()
+                + block
+                    .instructions
+                    .last()
+                    .map(|instruction| instruction.byte_length)
+                    .unwrap_or(0);
+
+            // The fall-through address is wherever this block's own instructions lead,
+            // per `Block::steps_to`, or the end of the sequence if this is the last
+            // block. Deriving this from an address-sorted list of block starts instead
+            // would get it wrong whenever `self.blocks` contains a block that is out of
+            // address order relative to its actual successor in the sequence.
+            let fallthrough = block.steps_to.unwrap_or(self.address_range.end);
+
+            let terminator = classify_terminator(debug_info, block_start, block_end, fallthrough);
+
+            self.successors
+                .insert(block_start, terminator_successors(&terminator));
+        }
+    }
+}
+
+/// Build the [`SequenceData`] for a [`gimli::LineSequence`]: its instructions, split
+/// into blocks, with their control-flow graph. Shared by
+/// [`Sequence::from_line_sequence`] and the cache-miss path of
+/// [`Sequence::from_address_cached`].
+fn build_sequence_data(
+    debug_info: &DebugInfo,
+    program_unit: &UnitInfo,
+    complete_line_program: gimli::CompleteLineProgram<LineReader, usize>,
+    line_sequence: &LineSequence<LineReader>,
+) -> Result<SequenceData, DebugError> {
+    let program_language = program_unit.get_language();
+    let mut sequence_rows = complete_line_program.resume_from(line_sequence);
+
+    let mut data = SequenceData {
+        address_range: line_sequence.start..line_sequence.end,
+        last_halt_instruction: None,
+        blocks: Vec::new(),
+        successors: BTreeMap::new(),
+        disassembly_index: std::cell::RefCell::new(None),
+    };
+
+    // Temporarily collect all the instructions in the sequence, before we re-process them to create the blocks.
+    let mut sequence_instructions: Vec<Instruction> = Vec::new();
+    let mut prologue_completed = false;
+    let mut previous_row: Option<gimli::LineRow> = None;
+
+    while let Ok(Some((_, row))) = sequence_rows.next_row() {
+        if !prologue_completed && is_prologue_complete(row, program_language, previous_row) {
+            // This is the first row after the prologue.
+            prologue_completed = true;
+        }
+
+        // The end of the sequence is not a valid halt location,
+        // nor is it a valid instruction in the current sequence.
+        if row.end_sequence() {
+            break;
+        }
+
+        // We need to know the last halt location in the sequence,
+        // and since we are already iterating through the rows, we can do it here,
+        // instead of iterating through the instructions again during runtime.
+        if row.is_stmt() || row.epilogue_begin() {
+            data.last_halt_instruction = Some(row.address());
+        }
+
+        sequence_instructions.push(Instruction::from_line_row(prologue_completed, row));
+        previous_row = Some(*row);
+    }
+
+    // Now that every instruction's address is known, fill in each one's real
+    // byte length from the gap to the next instruction, before splitting them
+    // into blocks: `build_control_flow_graph` needs an accurate length for a
+    // block's last instruction to hand the decoder the right number of bytes. The
+    // very last instruction in the sequence has no next instruction to measure
+    // against, so it measures against the sequence's own end address instead.
+    instruction::fill_byte_lengths(&mut sequence_instructions, data.address_range.end);
+
+    // Now that we have all the instructions, we can create the blocks.
+    data.build_blocks(
+        debug_info,
+        program_unit,
+        &mut sequence_instructions.iter().peekable(),
+    )?;
+    data.build_control_flow_graph(debug_info);
+
+    //TODO: Create a test to compare the number of instructions in the sequence with the number of instructions in the blocks.
+    tracing::trace!(
+        "The `Sequence` has {} instructions, and {} blocks.",
+        sequence_instructions.len(),
+        data.blocks.len(),
+    );
+    tracing::trace!(
+        "\tThe blocks combined have a total of {} instructions",
+        data.blocks
+            .iter()
+            .map(|block| block.instructions.len())
+            .sum::<usize>()
+    );
+    Ok(data)
+}
+
+/// Keep track of all the instruction locations required to satisfy the operations of [`SteppingMode`].
+/// This is a list of target instructions, belonging to a [`gimli::LineSequence`],
+/// and filters it to only user code instructions (no prologue code, and no non-statement instructions),
+/// so that we are left only with what DWARF terms as "recommended breakpoint location".
+pub(crate) struct Sequence<'debug_info> {
+    /// The built blocks and control-flow graph for this sequence, shared via `Rc` so
+    /// that [`SequenceCache`] can reuse them across calls without rebuilding.
+    data: Rc<SequenceData>,
     /// Required to resolve information about function calls, etc.
     pub(crate) debug_info: &'debug_info DebugInfo,
     /// Required to resolve information about function calls, etc.
@@ -44,7 +431,7 @@ pub(crate) struct Sequence<'debug_info> {
 
 impl PartialEq for Sequence<'_> {
     fn eq(&self, other: &Self) -> bool {
-        self.address_range == other.address_range
+        self.data.address_range == other.data.address_range
     }
 }
 
@@ -55,9 +442,9 @@ impl Debug for Sequence<'_> {
         writeln!(
             f,
             "Sequence range: {:#010x}..{:#010x}",
-            self.address_range.start, self.address_range.end
+            self.data.address_range.start, self.data.address_range.end
         )?;
-        for block in &self.blocks {
+        for block in &self.data.blocks {
             if let Some(included_addresses) = block.included_addresses() {
                 write!(
                     f,
@@ -155,109 +542,193 @@ impl<'debug_info> Sequence<'debug_info> {
         }
     }
 
-    /// Build [`Sequence`] from a [`gimli::LineSequence`], with all the markers we need to determine valid halt locations.
-    pub(crate) fn from_line_sequence(
+    /// Like [`Sequence::from_address`], but goes through `cache` instead of
+    /// re-decoding the unit's line-number program, and re-running
+    /// `build_blocks`/`build_control_flow_graph`, on every call. Hot stepping paths
+    /// (single-step, next-breakpoint resolution) should prefer this one, since stepping
+    /// through a tight loop would otherwise repeatedly redo both the line-program
+    /// decode and the block/control-flow-graph construction for the same sequence;
+    /// one-shot callers such as initial breakpoint verification can keep using the
+    /// plain constructor.
+    pub(crate) fn from_address_cached(
         debug_info: &'debug_info DebugInfo,
-        program_unit: &'debug_info UnitInfo,
-        complete_line_program: gimli::CompleteLineProgram<
-            gimli::EndianReader<gimli::LittleEndian, std::rc::Rc<[u8]>>,
-            usize,
-        >,
-        line_sequence: &LineSequence<gimli::EndianReader<gimli::LittleEndian, std::rc::Rc<[u8]>>>,
+        cache: &SequenceCache,
+        program_counter: u64,
     ) -> Result<Self, DebugError> {
-        let program_language = program_unit.get_language();
-        let mut sequence_rows = complete_line_program.resume_from(line_sequence);
-
-        // We have enough information to create the Sequence.
-        let mut sequence = Sequence {
-            address_range: line_sequence.start..line_sequence.end,
-            last_halt_instruction: None,
-            blocks: Vec::new(),
-            debug_info,
-            program_unit,
+        let program_unit = debug_info.compile_unit_info(program_counter)?;
+        let Some(line_program) = program_unit.unit.line_program.clone() else {
+            let message = "The specified source location does not have any line_program information available. Please consider using instruction level stepping.".to_string();
+            return Err(DebugError::WarnAndContinue { message });
         };
+        let offset = line_program.header().offset();
+        let address_size = line_program.header().address_size();
 
-        // Temporarily collect all the instructions in the sequence, before we re-process them to create the blocks.
-        let mut sequence_instructions: Vec<Instruction> = Vec::new();
-        let mut prologue_completed = false;
-        let mut previous_row: Option<gimli::LineRow> = None;
+        let cached = cache.get_or_decode(debug_info, offset, address_size)?;
 
-        while let Ok(Some((_, row))) = sequence_rows.next_row() {
-            if !prologue_completed && is_prologue_complete(row, program_language, previous_row) {
-                // This is the first row after the prologue.
-                prologue_completed = true;
+        let index = cached.line_sequences.binary_search_by(|line_sequence| {
+            if program_counter < line_sequence.start {
+                std::cmp::Ordering::Greater
+            } else if program_counter >= line_sequence.end {
+                std::cmp::Ordering::Less
+            } else {
+                std::cmp::Ordering::Equal
             }
+        });
+        let Ok(index) = index else {
+            let message = "The specified source location does not have any line information available. Please consider using instruction level stepping.".to_string();
+            return Err(DebugError::WarnAndContinue { message });
+        };
+        let line_sequence = &cached.line_sequences[index];
 
-            // The end of the sequence is not a valid halt location,
-            // nor is it a valid instruction in the current sequence.
-            if row.end_sequence() {
-                break;
-            }
+        let data = if let Some(data) = cache.sequence_data(offset, line_sequence.start) {
+            data
+        } else {
+            let built = build_sequence_data(
+                debug_info,
+                program_unit,
+                cached.complete_line_program.clone(),
+                line_sequence,
+            )?;
+            let built = Rc::new(built);
+            cache.cache_sequence_data(offset, line_sequence.start, built.clone());
+            built
+        };
 
-            // We need to know the last halt location in the sequence,
-            // and since we are already iterating through the rows, we can do it here,
-            // instead of iterating through the instructions again during runtime.
-            if row.is_stmt() || row.epilogue_begin() {
-                sequence.last_halt_instruction = Some(row.address());
-            }
+        let sequence = Sequence {
+            data,
+            debug_info,
+            program_unit,
+        };
 
-            sequence_instructions.push(Instruction::from_line_row(
-                prologue_completed,
-                row,
-                previous_row.as_ref(),
-            ));
-            previous_row = Some(*row);
+        if sequence.len() == 0 {
+            let message = "Could not find valid instruction locations for this address. Consider using instruction level stepping.".to_string();
+            Err(DebugError::WarnAndContinue { message })
+        } else {
+            tracing::trace!(
+                "Instruction location for pc={:#010x} (cached)\n{:?}",
+                program_counter,
+                sequence
+            );
+            Ok(sequence)
         }
+    }
 
-        // Now that we have all the instructions, we can create the blocks.
-        sequence.build_blocks(
+    /// Build [`Sequence`] from a [`gimli::LineSequence`], with all the markers we need to determine valid halt locations.
+    pub(crate) fn from_line_sequence(
+        debug_info: &'debug_info DebugInfo,
+        program_unit: &'debug_info UnitInfo,
+        complete_line_program: gimli::CompleteLineProgram<LineReader, usize>,
+        line_sequence: &LineSequence<LineReader>,
+    ) -> Result<Self, DebugError> {
+        let data = build_sequence_data(
             debug_info,
             program_unit,
-            &mut sequence_instructions.iter().peekable(),
+            complete_line_program,
+            line_sequence,
         )?;
 
-        //TODO: Create a test to compare the number of instructions in the sequence with the number of instructions in the blocks.
-        tracing::trace!(
-            "The `Sequence` has {} instructions, and {} blocks.",
-            sequence_instructions.len(),
-            sequence.blocks.len(),
-        );
-        tracing::trace!(
-            "\tThe blocks combined have a total of {} instructions",
-            sequence
-                .blocks
-                .iter()
-                .map(|block| block.instructions.len())
-                .sum::<usize>()
-        );
+        let sequence = Sequence {
+            data: Rc::new(data),
+            debug_info,
+            program_unit,
+        };
         tracing::trace!("{sequence:?}");
         Ok(sequence)
     }
 
-    /// Process instructions into blocks, based on their definition,
-    /// position in the sequence, and other debug information.
-    /// Returns the address of the last instruction in the block.
-    fn build_blocks(
-        &mut self,
-        debug_info: &'debug_info DebugInfo,
-        program_unit: &'debug_info UnitInfo,
-        block_instructions: &mut std::iter::Peekable<std::slice::Iter<Instruction>>,
-    ) -> Result<(), DebugError> {
-        while let Some(instruction) = block_instructions.peek() {
-            let current_block = Block::new(
-                instruction.address,
-                block_instructions,
-                debug_info,
-                program_unit,
-            )?;
-            self.blocks.push(current_block);
+    /// Get the number of instruction locations in the list.
+    pub(crate) fn len(&self) -> usize {
+        self.data.blocks.len()
+    }
+
+    /// Breadth-first search over [`SequenceData::successors`], starting at the block
+    /// beginning at `block_start`, for the first halt location, at or after `address`,
+    /// in any block reachable from it. Shared by [`Sequence::haltpoint_for_address`]
+    /// and [`Sequence::haltpoint_for_next_block`], so that a block ending in a
+    /// conditional branch, an indirect branch through a jump table, or a tail call is
+    /// not assumed to have only one way out: every block control may actually reach is
+    /// examined, and the first halt location found in address-of-discovery order is
+    /// returned. A block with no successors (e.g. it ends in a `return`) simply
+    /// contributes no candidate, rather than incorrectly chaining past the end of the
+    /// sequence. The `address` filter matters once the graph has a back edge (e.g. a
+    /// loop): without it, a visited block whose own address precedes `address` (the
+    /// loop header, say) could otherwise be reported as the next halt location, making
+    /// stepping appear to go backwards.
+    fn first_halt_instruction_reachable_from(
+        &self,
+        block_start: u64,
+        address: u64,
+    ) -> Option<&Instruction> {
+        let mut visited = BTreeSet::new();
+        let mut queue: VecDeque<u64> = self
+            .data
+            .successors
+            .get(&block_start)
+            .cloned()
+            .unwrap_or_default()
+            .into();
+
+        while let Some(next_address) = queue.pop_front() {
+            if !visited.insert(next_address) {
+                continue;
+            }
+            let Some(next_block) = self
+                .data
+                .blocks
+                .iter()
+                .find(|next_block| next_block.contains_address(next_address))
+            else {
+                continue;
+            };
+            if let Some(instruction) = next_block.instructions.iter().find(|instruction| {
+                instruction.address >= address && instruction.role.is_halt_location()
+            }) {
+                return Some(instruction);
+            }
+            if let Some(next_block_start) =
+                next_block.included_addresses().map(|range| *range.start())
+            {
+                queue.extend(
+                    self.data
+                        .successors
+                        .get(&next_block_start)
+                        .cloned()
+                        .unwrap_or_default(),
+                );
+            }
         }
-        Ok(())
+        None
     }
 
-    /// Get the number of instruction locations in the list.
-    pub(crate) fn len(&self) -> usize {
-        self.blocks.len()
+    /// Collect the chain of call frames that contain `address`, innermost first. The
+    /// outermost frame is always the concrete (non-inlined) function; every frame after
+    /// it represents one `DW_TAG_inlined_subroutine` that `address` falls inside of.
+    /// Returns a single frame (just the concrete function) if `address` is not inside
+    /// any inlined routine, which is the common case.
+    fn inline_frames_for_address(&self, address: u64) -> Vec<InlineFrame> {
+        let Ok(function_dies) = self
+            .program_unit
+            .get_function_dies(self.debug_info, address, true)
+        else {
+            return Vec::new();
+        };
+        let Some(innermost_location) =
+            SourceLocation::from_instruction_address(self.debug_info, self.program_unit, address)
+        else {
+            return Vec::new();
+        };
+
+        let names_and_call_sites: Vec<_> = function_dies
+            .iter()
+            .map(|function_die| {
+                (
+                    function_die.function_name(self.debug_info),
+                    function_die.call_site_location(self.debug_info),
+                )
+            })
+            .collect();
+
+        reorder_inline_frames(&names_and_call_sites, innermost_location)
     }
 
     /// See [`VerifiedBreakpoint::for_address()`].
@@ -266,6 +737,7 @@ impl<'debug_info> Sequence<'debug_info> {
         tracing::debug!("Looking for halt instruction at address={address:#010x}");
 
         let Some(block) = self
+            .data
             .blocks
             .iter()
             .find(|block| block.contains_address(address))
@@ -282,24 +754,13 @@ impl<'debug_info> Sequence<'debug_info> {
             // We found a matching halt location in the current block.
             Some(instruction)
         } else {
-            // Look for the next halt instruction in any blocks that we know are linked.
-            let mut halt_instruction = None;
-            let mut linked_address = block.steps_to;
-            while let Some(linked_block) = self.blocks.iter().find(|next_block| {
-                linked_address.is_some()
-                    && linked_address
-                        .map(|linked_address| next_block.contains_address(linked_address))
-                        .unwrap_or(false)
-            }) {
-                linked_address = linked_block.steps_to;
-                if let Some(instruction) = linked_block.instructions.iter().find(|instruction| {
-                    instruction.address >= address && instruction.role.is_halt_location()
-                }) {
-                    halt_instruction = Some(instruction);
-                    break;
-                }
-            }
-            halt_instruction
+            // Look for the next halt instruction in any block reachable from this one,
+            // via the same control-flow graph walk as `haltpoint_for_next_block`, so
+            // the two don't disagree about what "reachable" means for a block that
+            // ends in a conditional branch, an indirect branch, or a tail call.
+            block.included_addresses().and_then(|range| {
+                self.first_halt_instruction_reachable_from(*range.start(), address)
+            })
         };
 
         if let Some(breakpoint) = halt_instruction.and_then(|instruction| {
@@ -307,6 +768,7 @@ impl<'debug_info> Sequence<'debug_info> {
                 |source_location| VerifiedBreakpoint {
                     address: instruction.address,
                     source_location,
+                    inline_frames: self.inline_frames_for_address(instruction.address),
                 },
             )
         }) {
@@ -319,12 +781,19 @@ impl<'debug_info> Sequence<'debug_info> {
     }
 
     // TODO: We need tests for the various scenarios below.
-    /// If the current instruction is in a ['Block'], find the next valid halt location in the
-    /// next linked block in the sequence.
+    /// If the current instruction is in a ['Block'], find the next valid halt location
+    /// reachable from it in the sequence. Unlike a single linear `steps_to` link, this
+    /// walks every edge in [`SequenceData::successors`] breadth-first, so a block that ends
+    /// in a conditional branch, an indirect branch through a jump table, or a tail call
+    /// is not assumed to have only one way out: we look in every block that control may
+    /// actually reach, and return the first halt location found in address-of-discovery
+    /// order. A block with no successors (e.g. it ends in a `return`) simply yields no
+    /// candidate here, rather than incorrectly chaining past the end of the sequence.
     pub(crate) fn haltpoint_for_next_block(&self, address: u64) -> Option<VerifiedBreakpoint> {
         tracing::debug!("Looking for next block halt instruction at address={address:#010x}");
 
         let Some(block) = self
+            .data
             .blocks
             .iter()
             .find(|block| block.contains_address(address))
@@ -332,32 +801,22 @@ impl<'debug_info> Sequence<'debug_info> {
             tracing::warn!("Could not find a valid breakpoint for address={address:#010x}");
             return None;
         };
+        let Some(block_start) = block.included_addresses().map(|range| *range.start()) else {
+            tracing::warn!("Could not find a valid breakpoint for address={address:#010x}");
+            return None;
+        };
 
-        // Cycle through increasing degrees of "looseness" in the search for the halt instruction.
-
-        // Look for the next halt instruction in any blocks that we know are linked.
-        let mut halt_instruction = None;
-        let mut linked_address = block.steps_to;
-        while let Some(linked_block) = self.blocks.iter().find(|next_block| {
-            linked_address.is_some()
-                && linked_address
-                    .map(|linked_address| next_block.contains_address(linked_address))
-                    .unwrap_or(false)
-        }) {
-            linked_address = linked_block.steps_to;
-            if let Some(instruction) = linked_block.instructions.iter().find(|instruction| {
-                instruction.address >= address && instruction.role.is_halt_location()
-            }) {
-                halt_instruction = Some(instruction);
-                break;
-            }
-        }
+        // No lower address bound here: once we have stepped into the next block, any
+        // halt location in a block reachable from it is a valid "next" location,
+        // including the loop header if `address`'s block branches back to one.
+        let halt_instruction = self.first_halt_instruction_reachable_from(block_start, 0);
 
         if let Some(breakpoint) = halt_instruction.and_then(|instruction| {
             SourceLocation::from_instruction(self.debug_info, self.program_unit, instruction).map(
                 |source_location| VerifiedBreakpoint {
                     address: instruction.address,
                     source_location,
+                    inline_frames: self.inline_frames_for_address(instruction.address),
                 },
             )
         }) {
@@ -380,17 +839,21 @@ impl<'debug_info> Sequence<'debug_info> {
         column: Option<u64>,
     ) -> Option<VerifiedBreakpoint> {
         tracing::debug!(
-            "Looking for a breakpoint for line={line}, column={} in file: {}",
-            column.unwrap(),
-            self.debug_info
-                .get_path(&self.program_unit.unit, matching_file_index.unwrap())
-                .unwrap()
-                .to_string_lossy()
+            "Looking for a breakpoint for line={line}, column={:?} in file: {}",
+            column,
+            matching_file_index
+                .and_then(|file_index| self.debug_info.get_path(&self.program_unit.unit, file_index))
+                .map(
+                    |file_path| TypedPathBuf::from_unix(file_path.file_name().unwrap())
+                        .to_string_lossy()
+                        .to_string()
+                )
+                .unwrap_or("<unknown file>".to_string())
         );
         // Cycle through various degrees of matching, to find the most relevant source location.
         // We have to do this in multiple iterations because instructions are allocated to blocks
         // based on their instruction address, and not based on their source location.
-        for block in &self.blocks {
+        for block in &self.data.blocks {
             // Try an exact match.
             if let Some(matching_breakpoint) = block
                 .instructions
@@ -415,15 +878,240 @@ impl<'debug_info> Sequence<'debug_info> {
         }
 
         tracing::warn!(
-            "Could not find a valid breakpoint for line={line}, column={} in file: {}",
-            column.unwrap(),
-            self.debug_info
-                .get_path(&self.program_unit.unit, matching_file_index.unwrap())
-                .unwrap()
-                .to_string_lossy()
+            "Could not find a valid breakpoint for line={line}, column={:?} in file: {}",
+            column,
+            matching_file_index
+                .and_then(|file_index| self.debug_info.get_path(&self.program_unit.unit, file_index))
+                .map(
+                    |file_path| TypedPathBuf::from_unix(file_path.file_name().unwrap())
+                        .to_string_lossy()
+                        .to_string()
+                )
+                .unwrap_or("<unknown file>".to_string())
         );
         None
     }
+
+    /// Return every recommended halt location (`(address, SourceLocation)`) whose
+    /// address falls inside `range`, in increasing address order, spanning as many
+    /// blocks as the range covers. Reuses the same statement/epilogue-begin filtering as
+    /// [`Sequence::haltpoint_for_address`]. This lets a front-end populate a "set
+    /// breakpoint on any of these lines" gutter for a whole function or a visible
+    /// disassembly window in one call, instead of probing address by address.
+    pub(crate) fn halt_locations_in_range(&self, range: Range<u64>) -> Vec<(u64, SourceLocation)> {
+        let mut locations: Vec<(u64, SourceLocation)> = self
+            .data
+            .blocks
+            .iter()
+            .filter(|block| {
+                block.included_addresses().is_some_and(|addresses| {
+                    *addresses.start() < range.end && *addresses.end() >= range.start
+                })
+            })
+            .flat_map(|block| {
+                let range = range.clone();
+                block.instructions.iter().filter(move |instruction| {
+                    instruction.role.is_halt_location() && range.contains(&instruction.address)
+                })
+            })
+            .filter_map(|instruction| {
+                SourceLocation::from_instruction(self.debug_info, self.program_unit, instruction)
+                    .map(|source_location| (instruction.address, source_location))
+            })
+            .collect();
+
+        // `SequenceData::blocks` is not guaranteed to be address-sorted (see the note
+        // on that field), so the filter/flat_map above can yield locations out of
+        // order; sort explicitly to honor the "increasing address order" this method
+        // promises.
+        locations.sort_by_key(|(address, _)| *address);
+        locations
+    }
+
+    /// Produce a flat, address-ordered disassembly listing suitable for the Debug
+    /// Adapter Protocol `disassemble` request. Addresses are plain integers, not opaque
+    /// location handles, since that is what VS Code's disassembly view expects to key
+    /// its rows on. Source info is attached only to the first instruction of each new
+    /// source line, so a client can render interleaved source without repeating it for
+    /// every instruction on that line.
+    pub(crate) fn disassembly_listing(&self) -> Vec<DisassembledInstruction> {
+        let mut instructions: Vec<&Instruction> = self
+            .data
+            .blocks
+            .iter()
+            .flat_map(|block| block.instructions.iter())
+            .collect();
+        // `SequenceData::blocks` is not guaranteed to be address-sorted (see the note
+        // on that field), so sort explicitly: the listing must be address-ordered both
+        // for the DAP client and for the "new source line" tracking below, which only
+        // makes sense walked in address order.
+        instructions.sort_by_key(|instruction| instruction.address);
+
+        let mut listing = Vec::with_capacity(instructions.len());
+        let mut last_source_line: Option<(u64, NonZeroU64)> = None;
+
+        for instruction in instructions {
+            let current_line = instruction.line.map(|line| (instruction.file_index, line));
+            let source_location = if current_line.is_some() && current_line != last_source_line {
+                last_source_line = current_line;
+                SourceLocation::from_instruction(self.debug_info, self.program_unit, instruction)
+            } else {
+                None
+            };
+
+            listing.push(DisassembledInstruction {
+                address: instruction.address,
+                byte_length: instruction.byte_length(),
+                source_location,
+            });
+        }
+
+        listing
+    }
+
+    /// Find the position of `address` in [`Sequence::disassembly_listing`], so a
+    /// front-end can highlight the current instruction, and so a `disassemble` request
+    /// with a negative `instructionOffset` can walk backward across block boundaries.
+    /// Builds [`SequenceData::disassembly_index`] on first use and reuses it
+    /// afterwards - shared via the same `Rc<SequenceData>` as every other [`Sequence`]
+    /// for this sequence, since this is called on every single step to re-highlight the
+    /// current instruction.
+    pub(crate) fn disassembly_index_for_address(&self, address: u64) -> Option<usize> {
+        self.data.disassembly_index_for_address(address)
+    }
+}
+
+/// One entry in a DAP-compatible disassembly listing. See
+/// [`Sequence::disassembly_listing`] and [`program_disassembly_listing`].
+#[derive(Debug, Clone)]
+pub(crate) struct DisassembledInstruction {
+    /// The instruction's absolute address.
+    pub(crate) address: u64,
+    /// The instruction's length in bytes.
+    pub(crate) byte_length: u64,
+    /// The resolved source location, present only on the first instruction of a new
+    /// source line.
+    pub(crate) source_location: Option<SourceLocation>,
+}
+
+/// Aggregate [`Sequence::disassembly_listing`] over every sequence in a program, into
+/// one flat, address-ordered DAP `disassemble` listing. `sequences` is expected to
+/// already be in address order; callers holding sequences in another order should sort
+/// by `address_range.start` first.
+pub(crate) fn program_disassembly_listing(sequences: &[Sequence]) -> Vec<DisassembledInstruction> {
+    sequences
+        .iter()
+        .flat_map(|sequence| sequence.disassembly_listing())
+        .collect()
+}
+
+/// Reorder a chain of function DIEs (outermost, concrete function first; innermost
+/// `DW_TAG_inlined_subroutine` last) into a synthetic call stack, innermost first: the
+/// innermost frame's location is `innermost_location` (the halt address itself); every
+/// frame further out is located at the call site of the frame one level in, since a
+/// DIE's own `DW_AT_call_file`/`DW_AT_call_line`/`DW_AT_call_column` records where *it*
+/// was called from, which is where its caller's frame "is", not where it itself is.
+fn reorder_inline_frames(
+    dies: &[(Option<String>, Option<SourceLocation>)],
+    innermost_location: SourceLocation,
+) -> Vec<InlineFrame> {
+    let mut frames = Vec::with_capacity(dies.len());
+    let mut location = innermost_location;
+    for (function_name, call_site) in dies.iter().rev() {
+        frames.push(InlineFrame {
+            function_name: function_name.clone(),
+            location: location.clone(),
+        });
+        if let Some(call_site) = call_site {
+            location = call_site.clone();
+        }
+    }
+    frames
+}
+
+/// Classify a block's terminating instruction, and if it is an indirect branch,
+/// resolve its jump table targets. Falls back to treating the block as a plain
+/// fall-through if the target's raw instruction bytes are not available, which keeps
+/// this usable even when no [`TerminatorDecoder`] has been wired up for the current
+/// architecture yet.
+fn classify_terminator(
+    debug_info: &DebugInfo,
+    block_start: u64,
+    block_end: u64,
+    fallthrough: u64,
+) -> Terminator {
+    let (Some(decoder), Some(block_bytes)) = (
+        debug_info.terminator_decoder(),
+        debug_info.program_bytes_in_range(block_start..block_end),
+    ) else {
+        return Terminator::FallThrough(fallthrough);
+    };
+
+    match decoder.classify(block_start, block_bytes, fallthrough) {
+        Terminator::IndirectBranch(_) => {
+            // A PC-relative table (the only kind a single block's bytes let us resolve)
+            // lives immediately after the dispatching instruction, so hand the decoder
+            // up to `MAX_JUMP_TABLE_ENTRIES` halfwords of whatever program data follows
+            // the block; it reads only as much of that as the table format it detects
+            // actually needs.
+            let table_bytes = debug_info
+                .program_bytes_in_range(
+                    block_end..block_end + (MAX_JUMP_TABLE_ENTRIES as u64) * 2,
+                )
+                .unwrap_or(&[]);
+            let targets = decoder.resolve_jump_table(
+                block_bytes,
+                block_start,
+                table_bytes,
+                MAX_JUMP_TABLE_ENTRIES,
+            );
+            if targets.is_empty() {
+                // Couldn't resolve the table; don't pretend we know where control goes.
+                Terminator::IndirectBranch(Vec::new())
+            } else {
+                Terminator::IndirectBranch(targets)
+            }
+        }
+        other => other,
+    }
+}
+
+/// Every address, among `instructions`, that has to be the start of its own block:
+/// wherever an instruction's own bytes classify as a real terminator (anything but a
+/// plain fall-through into the very next instruction), both the address right after it
+/// and every address it can transfer control to become block starts. Each instruction is
+/// classified using only its own bytes - not whatever block it will end up in - since
+/// `classify_terminator` only looks at the last instruction in whatever range it is
+/// given; passing just one instruction's range is what lets this run before blocks
+/// exist at all, rather than only at whatever address happens to be the last
+/// instruction of a block split purely on inlining.
+fn branch_target_addresses(
+    debug_info: &DebugInfo,
+    instructions: &[Instruction],
+    sequence_end: u64,
+) -> BTreeSet<u64> {
+    let mut block_starts = BTreeSet::new();
+    for (index, instruction) in instructions.iter().enumerate() {
+        let fallthrough = instructions
+            .get(index + 1)
+            .map(|next| next.address)
+            .unwrap_or(sequence_end);
+
+        let terminator = classify_terminator(
+            debug_info,
+            instruction.address,
+            instruction.address + instruction.byte_length,
+            fallthrough,
+        );
+
+        if matches!(&terminator, Terminator::FallThrough(target) if *target == fallthrough) {
+            continue;
+        }
+
+        block_starts.insert(fallthrough);
+        block_starts.extend(terminator_successors(&terminator));
+    }
+    block_starts
 }
 
 /// Test if the current row signals that we are beyond the prologue, and into user code
@@ -455,3 +1143,49 @@ fn is_prologue_complete(
     }
     prologue_completed
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn location(address: u64) -> SourceLocation {
+        SourceLocation {
+            file_index: 0,
+            line: NonZeroU64::new(address),
+            column: ColumnType::from(0),
+            address,
+        }
+    }
+
+    #[test]
+    fn inline_frames_attach_call_sites_to_the_enclosing_frame_not_their_own() {
+        // main (concrete) -> middle (inlined, called from main at line 10) -> inner
+        // (inlined, called from middle at line 20). Halt address resolves to line 42,
+        // inside `inner`.
+        let dies = vec![
+            (Some("main".to_string()), None),
+            (Some("middle".to_string()), Some(location(10))),
+            (Some("inner".to_string()), Some(location(20))),
+        ];
+
+        let frames = reorder_inline_frames(&dies, location(42));
+
+        let names_and_lines: Vec<_> = frames
+            .iter()
+            .map(|frame| {
+                (
+                    frame.function_name.clone(),
+                    frame.location.line.map(NonZeroU64::get),
+                )
+            })
+            .collect();
+        assert_eq!(
+            names_and_lines,
+            vec![
+                (Some("inner".to_string()), Some(42)),
+                (Some("middle".to_string()), Some(20)),
+                (Some("main".to_string()), Some(10)),
+            ]
+        );
+    }
+}
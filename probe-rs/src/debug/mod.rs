@@ -0,0 +1,206 @@
+pub(crate) mod halting;
+pub(crate) mod unit_info;
+
+use halting::decoder::ThumbTerminatorDecoder;
+use halting::sequence::{Sequence, SequenceCache, TerminatorDecoder};
+use std::{num::NonZeroU64, rc::Rc};
+use typed_path::TypedPathBuf;
+use unit_info::UnitInfo;
+
+/// The `gimli` reader type used throughout DWARF/`.debug_line` parsing.
+type Reader = gimli::EndianReader<gimli::LittleEndian, Rc<[u8]>>;
+
+/// Errors produced while resolving debug information. `WarnAndContinue` covers
+/// everything that should surface as a warning to the user rather than abort whatever
+/// larger operation (e.g. setting a breakpoint) triggered it.
+#[derive(Debug)]
+pub(crate) enum DebugError {
+    WarnAndContinue { message: String },
+}
+
+impl std::fmt::Display for DebugError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DebugError::WarnAndContinue { message } => write!(f, "{message}"),
+        }
+    }
+}
+
+impl std::error::Error for DebugError {}
+
+impl From<gimli::Error> for DebugError {
+    fn from(error: gimli::Error) -> Self {
+        DebugError::WarnAndContinue {
+            message: error.to_string(),
+        }
+    }
+}
+
+/// A DWARF column number. `0` means "left edge" (DWARF's way of saying "no specific
+/// column").
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) struct ColumnType(pub(crate) u64);
+
+impl From<u64> for ColumnType {
+    fn from(value: u64) -> Self {
+        ColumnType(value)
+    }
+}
+
+/// A resolved `file:line:column` for a single address.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SourceLocation {
+    pub(crate) file_index: u64,
+    pub(crate) line: Option<NonZeroU64>,
+    pub(crate) column: ColumnType,
+    pub(crate) address: u64,
+}
+
+impl SourceLocation {
+    /// Resolve the source location of an already-decoded instruction.
+    pub(crate) fn from_instruction(
+        _debug_info: &DebugInfo,
+        _program_unit: &UnitInfo,
+        instruction: &halting::instruction::Instruction,
+    ) -> Option<Self> {
+        Some(SourceLocation {
+            file_index: instruction.file_index,
+            line: instruction.line,
+            column: instruction.column,
+            address: instruction.address,
+        })
+    }
+
+    /// Resolve the source location for a bare address. Used wherever we only have an
+    /// address and not an already-decoded [`Instruction`](halting::instruction::Instruction),
+    /// e.g. the innermost frame of a synthetic inline call stack.
+    pub(crate) fn from_instruction_address(
+        debug_info: &DebugInfo,
+        program_unit: &UnitInfo,
+        address: u64,
+    ) -> Option<Self> {
+        let line_program = program_unit.unit.line_program.clone()?;
+        let offset = line_program.header().offset();
+        let address_size = line_program.header().address_size();
+
+        let incomplete_line_program = debug_info
+            .debug_line_section
+            .program(offset, address_size, None, None)
+            .ok()?;
+        let (complete_line_program, line_sequences) = incomplete_line_program.sequences().ok()?;
+        let line_sequence = line_sequences
+            .iter()
+            .find(|line_sequence| line_sequence.start <= address && address < line_sequence.end)?;
+
+        let mut rows = complete_line_program.resume_from(line_sequence);
+        let mut matching_row: Option<gimli::LineRow> = None;
+        while let Ok(Some((_, row))) = rows.next_row() {
+            if row.end_sequence() || row.address() > address {
+                break;
+            }
+            matching_row = Some(*row);
+        }
+        let row = matching_row?;
+
+        Some(SourceLocation {
+            file_index: row.file_index(),
+            line: row.line(),
+            column: ColumnType::from(match row.column() {
+                gimli::ColumnType::LeftEdge => 0,
+                gimli::ColumnType::Column(column) => column.get(),
+            }),
+            address,
+        })
+    }
+}
+
+/// Parsed debug information for one loaded program: DWARF, line-number programs, and
+/// (for control-flow analysis) the architecture-specific instruction decoder.
+pub(crate) struct DebugInfo {
+    pub(crate) debug_line_section: gimli::DebugLine<Reader>,
+    units: Vec<UnitInfo>,
+    /// The raw bytes of the executable code, and the address its first byte loads at,
+    /// so [`DebugInfo::program_bytes_in_range`] can hand a block's raw instruction
+    /// bytes to a [`TerminatorDecoder`].
+    program_bytes: Rc<[u8]>,
+    program_base_address: u64,
+    terminator_decoder: Box<dyn TerminatorDecoder>,
+    /// Parsed line-number program data for units we've already resolved a [`Sequence`]
+    /// in, so that repeated stepping through the same compilation unit doesn't
+    /// re-decode its `.debug_line` program every time. See [`SequenceCache`].
+    sequence_cache: SequenceCache,
+}
+
+impl DebugInfo {
+    /// Resolve the [`Sequence`] covering `program_counter`, going through
+    /// [`Self::sequence_cache`] instead of re-decoding the owning unit's line-number
+    /// program on every call. This is the entry point stepping and "next breakpoint"
+    /// resolution should use; one-shot callers such as initial breakpoint verification
+    /// can use [`Sequence::from_address`] directly instead.
+    pub(crate) fn sequence_for_step(&self, program_counter: u64) -> Result<Sequence<'_>, DebugError> {
+        Sequence::from_address_cached(self, &self.sequence_cache, program_counter)
+    }
+
+    /// Drop all cached line-number program data. Must be called whenever this
+    /// `DebugInfo` is reloaded from a new ELF/DWARF image, since every entry in
+    /// [`Self::sequence_cache`] is only valid for the image it was decoded from.
+    pub(crate) fn reload(&mut self) {
+        self.sequence_cache.clear();
+    }
+
+    pub(crate) fn compile_unit_info(&self, program_counter: u64) -> Result<&UnitInfo, DebugError> {
+        // Prefer the unit whose own `DW_AT_low_pc..DW_AT_high_pc` range actually
+        // contains `program_counter`, so that a multi-CU image resolves breakpoints
+        // and sequences against the right unit's line program. Fall back to the first
+        // unit whose range is unknown (e.g. a `DW_AT_ranges`-based unit, which
+        // `UnitInfo::pc_range` can't resolve without a `.debug_rnglists` section), on
+        // the assumption that it's still more useful than giving up entirely.
+        self.units
+            .iter()
+            .find(|unit| {
+                unit.pc_range()
+                    .is_some_and(|range| range.contains(&program_counter))
+            })
+            .or_else(|| self.units.first())
+            .ok_or_else(|| DebugError::WarnAndContinue {
+                message: format!("No compilation unit found for address {program_counter:#010x}"),
+            })
+    }
+
+    pub(crate) fn get_path(
+        &self,
+        _unit: &gimli::Unit<Reader, usize>,
+        _file_index: u64,
+    ) -> Option<TypedPathBuf> {
+        None
+    }
+
+    /// The raw instruction bytes covering `range`, if it falls entirely inside the
+    /// loaded program image.
+    pub(crate) fn program_bytes_in_range(&self, range: std::ops::Range<u64>) -> Option<&[u8]> {
+        let start = range.start.checked_sub(self.program_base_address)?;
+        let end = range.end.checked_sub(self.program_base_address)?;
+        self.program_bytes
+            .get(usize::try_from(start).ok()?..usize::try_from(end).ok()?)
+    }
+
+    /// The architecture-specific decoder used to classify a block's terminating
+    /// instruction. `None` if this target's architecture has no decoder registered
+    /// yet, in which case callers fall back to a conservative fall-through assumption.
+    pub(crate) fn terminator_decoder(&self) -> Option<&dyn TerminatorDecoder> {
+        Some(self.terminator_decoder.as_ref())
+    }
+}
+
+impl Default for DebugInfo {
+    fn default() -> Self {
+        DebugInfo {
+            debug_line_section: gimli::DebugLine::new(&[], gimli::LittleEndian),
+            units: Vec::new(),
+            program_bytes: Rc::from(Vec::new().into_boxed_slice()),
+            program_base_address: 0,
+            terminator_decoder: Box::new(ThumbTerminatorDecoder),
+            sequence_cache: SequenceCache::default(),
+        }
+    }
+}
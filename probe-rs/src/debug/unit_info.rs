@@ -0,0 +1,174 @@
+use super::{ColumnType, DebugError, DebugInfo, Reader, SourceLocation};
+use std::num::NonZeroU64;
+use std::ops::Range;
+
+/// One DIE in the chain of function and inlined-subroutine DIEs that contain a given
+/// address, as returned by [`UnitInfo::get_function_dies`]. The chain runs from the
+/// concrete (outermost, non-inlined) function to the innermost
+/// `DW_TAG_inlined_subroutine`; this type only carries what
+/// [`Sequence`](super::halting::sequence::Sequence) needs to name a frame and, for an
+/// inlined frame, find its call site.
+#[derive(Debug, Clone)]
+pub(crate) struct FunctionDie {
+    name: Option<String>,
+    inline: bool,
+    call_site: Option<SourceLocation>,
+}
+
+impl FunctionDie {
+    pub(crate) fn function_name(&self, _debug_info: &DebugInfo) -> Option<String> {
+        self.name.clone()
+    }
+
+    /// Whether this DIE is a `DW_TAG_inlined_subroutine` rather than the concrete,
+    /// non-inlined function at the outermost end of the chain.
+    pub(crate) fn is_inline(&self) -> bool {
+        self.inline
+    }
+
+    /// Where this inlined routine was called from, i.e. this DIE's
+    /// `DW_AT_call_file`/`DW_AT_call_line`/`DW_AT_call_column`. `None` for the outermost,
+    /// non-inlined function, which has no call site of its own.
+    pub(crate) fn call_site_location(&self, _debug_info: &DebugInfo) -> Option<SourceLocation> {
+        self.call_site.clone()
+    }
+}
+
+/// Debug information scoped to a single compilation unit.
+pub(crate) struct UnitInfo {
+    pub(crate) unit: gimli::Unit<Reader, usize>,
+}
+
+impl UnitInfo {
+    pub(crate) fn get_language(&self) -> gimli::DwLang {
+        self.unit.language.unwrap_or(gimli::DW_LANG_C99)
+    }
+
+    /// The `DW_AT_low_pc..DW_AT_high_pc` range of this unit's root
+    /// (`DW_TAG_compile_unit`) DIE, if it has one, so that [`DebugInfo::compile_unit_info`]
+    /// can pick the unit that actually contains a given address instead of guessing.
+    pub(crate) fn pc_range(&self) -> Option<Range<u64>> {
+        let (_, root) = self.unit.entries().next_dfs().ok()??;
+        die_pc_range(root)
+    }
+
+    /// Return the chain of function DIEs containing `address`, ordered from the
+    /// concrete (outermost, non-inlined) function to the innermost
+    /// `DW_TAG_inlined_subroutine`. `include_inlined` controls whether inlined
+    /// subroutine DIEs are included at all, or only the concrete function.
+    ///
+    /// Only contiguous `DW_AT_low_pc`/`DW_AT_high_pc` ranges are understood; a DIE
+    /// whose extent is instead given by `DW_AT_ranges` is skipped, since this unit has
+    /// no `.debug_rnglists`/`.debug_ranges` section to resolve it against.
+    pub(crate) fn get_function_dies(
+        &self,
+        _debug_info: &DebugInfo,
+        address: u64,
+        include_inlined: bool,
+    ) -> Result<Vec<FunctionDie>, DebugError> {
+        let mut entries = self.unit.entries();
+        // The chain of DIEs, outermost first, that `address` is nested inside of, so
+        // far. Paired with the depth each entry was found at, so that when the cursor
+        // backtracks out of a subtree we can drop whatever we recorded for it without
+        // having to re-walk from the root.
+        let mut chain: Vec<(isize, FunctionDie)> = Vec::new();
+        let mut depth: isize = 0;
+
+        while let Some((delta_depth, entry)) = entries.next_dfs()? {
+            depth += delta_depth;
+
+            let is_inline = entry.tag() == gimli::DW_TAG_inlined_subroutine;
+            if entry.tag() != gimli::DW_TAG_subprogram && !is_inline {
+                continue;
+            }
+            if is_inline && !include_inlined {
+                continue;
+            }
+            let Some(pc_range) = die_pc_range(entry) else {
+                continue;
+            };
+            if !pc_range.contains(&address) {
+                continue;
+            }
+
+            // Drop anything recorded for a sibling subtree we've since backtracked out
+            // of: only entries strictly shallower than this one can still be our
+            // ancestors.
+            chain.retain(|(recorded_depth, _)| *recorded_depth < depth);
+            chain.push((
+                depth,
+                FunctionDie {
+                    name: die_name(entry),
+                    inline: is_inline,
+                    call_site: if is_inline {
+                        call_site_location(entry)
+                    } else {
+                        None
+                    },
+                },
+            ));
+        }
+
+        Ok(chain
+            .into_iter()
+            .map(|(_, function_die)| function_die)
+            .collect())
+    }
+}
+
+/// The `DW_AT_low_pc..DW_AT_high_pc` range of `entry`, if it has one. `DW_AT_high_pc`
+/// may be encoded either as an absolute address or as an offset from `DW_AT_low_pc`;
+/// both forms are handled.
+fn die_pc_range(entry: &gimli::DebuggingInformationEntry<Reader, usize>) -> Option<Range<u64>> {
+    let low_pc = match entry.attr_value(gimli::DW_AT_low_pc).ok()?? {
+        gimli::AttributeValue::Addr(addr) => addr,
+        _ => return None,
+    };
+    let high_pc = match entry.attr_value(gimli::DW_AT_high_pc).ok()?? {
+        gimli::AttributeValue::Addr(addr) => addr,
+        other => low_pc.checked_add(other.udata_value()?)?,
+    };
+    Some(low_pc..high_pc)
+}
+
+/// `entry`'s `DW_AT_name`, if present in the inline string form. A `DW_FORM_strp`
+/// reference into `.debug_str` cannot be resolved here, since this unit does not keep
+/// that section around; such a DIE is treated as unnamed rather than failing the whole
+/// lookup.
+fn die_name(entry: &gimli::DebuggingInformationEntry<Reader, usize>) -> Option<String> {
+    match entry.attr_value(gimli::DW_AT_name).ok()?? {
+        gimli::AttributeValue::String(slice) => Some(slice.to_string_lossy().into_owned()),
+        _ => None,
+    }
+}
+
+/// Where `entry` (a `DW_TAG_inlined_subroutine`) was called from, from its
+/// `DW_AT_call_file`/`DW_AT_call_line`/`DW_AT_call_column`. The resulting
+/// `SourceLocation::address` is left at `0`: a call site is not itself an instruction
+/// address, and nothing reads it back out.
+fn call_site_location(
+    entry: &gimli::DebuggingInformationEntry<Reader, usize>,
+) -> Option<SourceLocation> {
+    let file_index = entry
+        .attr_value(gimli::DW_AT_call_file)
+        .ok()??
+        .udata_value()?;
+    let line = entry
+        .attr_value(gimli::DW_AT_call_line)
+        .ok()
+        .flatten()
+        .and_then(|value| value.udata_value());
+    let column = entry
+        .attr_value(gimli::DW_AT_call_column)
+        .ok()
+        .flatten()
+        .and_then(|value| value.udata_value())
+        .unwrap_or(0);
+
+    Some(SourceLocation {
+        file_index,
+        line: line.and_then(NonZeroU64::new),
+        column: ColumnType::from(column),
+        address: 0,
+    })
+}